@@ -8,33 +8,27 @@ use crate::direction::Direction;
 
 pub struct AnimatedTexture<'a> {
     raw_texture: Texture<'a>,
+    animation: Animation,
+    offset: (i32, i32),
+    frame_width: u32,
+    frame_height: u32,
+}
+
+/// Which frame an animation is on, playing forwards then backwards through its frames.
+struct Animation {
     ticker: u32,
     reversed: bool,
-    offset: (i32, i32),
     ticks_per_frame: u32,
     frame_count: u32,
-    frame_width: u32,
-    frame_height: u32,
 }
 
-impl<'a> AnimatedTexture<'a> {
-    pub fn new(
-        texture: Texture<'a>,
-        ticks_per_frame: u32,
-        frame_count: u32,
-        frame_width: u32,
-        frame_height: u32,
-        offset: Option<(i32, i32)>,
-    ) -> Self {
-        AnimatedTexture {
-            raw_texture: texture,
+impl Animation {
+    fn new(ticks_per_frame: u32, frame_count: u32) -> Self {
+        Animation {
             ticker: 0,
             reversed: false,
             ticks_per_frame,
             frame_count,
-            frame_width,
-            frame_height,
-            offset: offset.unwrap_or((0, 0)),
         }
     }
 
@@ -44,7 +38,7 @@ impl<'a> AnimatedTexture<'a> {
     }
 
     // Move to the next frame. If we are at the end of the animation, reverse the direction
-    pub fn tick(&mut self) {
+    fn tick(&mut self) {
         if self.reversed {
             self.ticker -= 1;
 
@@ -60,9 +54,43 @@ impl<'a> AnimatedTexture<'a> {
         }
     }
 
+    // Ticks towards the given frame, stopping once it is reached
+    fn tick_towards(&mut self, frame: u32) {
+        let current = self.current_frame();
+
+        // If the frame we're targeting is behind the current direction of play, reverse now rather than
+        // playing out to the end of the strip and bouncing back.
+        if (self.reversed && frame > current) || (!self.reversed && frame < current) {
+            self.reversed = !self.reversed;
+        }
+
+        if frame != current {
+            self.tick();
+        }
+    }
+}
+
+impl<'a> AnimatedTexture<'a> {
+    pub fn new(
+        texture: Texture<'a>,
+        ticks_per_frame: u32,
+        frame_count: u32,
+        frame_width: u32,
+        frame_height: u32,
+        offset: Option<(i32, i32)>,
+    ) -> Self {
+        AnimatedTexture {
+            raw_texture: texture,
+            animation: Animation::new(ticks_per_frame, frame_count),
+            frame_width,
+            frame_height,
+            offset: offset.unwrap_or((0, 0)),
+        }
+    }
+
     // Calculate the frame rect (portion of the texture to render) for the given frame.
     fn get_frame_rect(&self, frame: u32) -> Rect {
-        if frame >= self.frame_count {
+        if frame >= self.animation.frame_count {
             panic!("Frame {} is out of bounds for this texture", frame);
         }
 
@@ -80,8 +108,13 @@ impl<'a> AnimatedTexture<'a> {
         position: (i32, i32),
         direction: Direction,
     ) {
-        self.render_static(canvas, position, direction, Some(self.current_frame()));
-        self.tick();
+        self.render_static(
+            canvas,
+            position,
+            direction,
+            Some(self.animation.current_frame()),
+        );
+        self.animation.tick();
     }
 
     // Functions like render, but only ticks the animation until the given frame is reached.
@@ -92,13 +125,9 @@ impl<'a> AnimatedTexture<'a> {
         direction: Direction,
         frame: u32,
     ) {
-        // TODO: If the frame we're targeting is in the opposite direction (due to self.reverse), we should pre-emptively reverse.
-        let current = self.current_frame();
+        let current = self.animation.current_frame();
         self.render_static(canvas, position, direction, Some(current));
-
-        if frame != current {
-            self.tick();
-        }
+        self.animation.tick_towards(frame);
     }
 
     // Renders a specific frame of the animation. Defaults to the current frame.
@@ -109,7 +138,7 @@ impl<'a> AnimatedTexture<'a> {
         direction: Direction,
         frame: Option<u32>,
     ) {
-        let frame_rect = self.get_frame_rect(frame.unwrap_or(self.current_frame()));
+        let frame_rect = self.get_frame_rect(frame.unwrap_or(self.animation.current_frame()));
         let position_rect = Rect::new(
             position.0 + self.offset.0,
             position.1 + self.offset.1,
@@ -130,3 +159,40 @@ impl<'a> AnimatedTexture<'a> {
             .expect("Could not render texture on canvas");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_forwards_and_backwards() {
+        let mut animation = Animation::new(2, 3);
+        let mut frames = Vec::new();
+        for _ in 0..10 {
+            frames.push(animation.current_frame());
+            animation.tick();
+        }
+
+        assert_eq!(frames, [0, 0, 1, 1, 2, 2, 2, 1, 1, 0]);
+    }
+
+    #[test]
+    fn reverses_towards_target_frame() {
+        let mut animation = Animation::new(2, 3);
+        // Play to the end and start back down to frame 1
+        for _ in 0..7 {
+            animation.tick();
+        }
+        assert!(animation.reversed);
+        assert_eq!(animation.current_frame(), 1);
+
+        // Frame 2 is ahead, so play forwards to it rather than bouncing off frame 0
+        let mut frames = Vec::new();
+        for _ in 0..10 {
+            animation.tick_towards(2);
+            frames.push(animation.current_frame());
+        }
+
+        assert_eq!(frames, [2; 10]);
+    }
+}
@@ -0,0 +1,117 @@
+use std::iter::Peekable;
+use std::path::PathBuf;
+
+use tracing::event;
+
+/// Command-line options passed at startup.
+///
+/// Arguments are only read on desktop; the Emscripten build has no command line and always uses the defaults.
+#[derive(Debug, Default)]
+pub struct Args {
+    // Start with debug rendering enabled
+    pub debug: bool,
+    // Board file to load instead of the built-in RAW_BOARD
+    pub map: Option<PathBuf>,
+    // Skip rendering every other frame while the game loop is running behind
+    pub adaptive_render: bool,
+    // Draw pellets and power pellets enlarged for visibility
+    pub high_contrast: bool,
+    // Start with the game running in slow motion
+    pub slow_motion: bool,
+    // Replace textures that can't be loaded with placeholders instead of exiting (for development)
+    pub lenient_assets: bool,
+    // Pause automatically when the window loses focus, resuming when it is regained
+    pub pause_on_focus_loss: bool,
+    // Ask for confirmation before quitting with Escape or Q
+    pub confirm_quit: bool,
+    // Check a board file for problems and exit, without starting the game
+    pub lint_map: Option<PathBuf>,
+    // With --lint-map, also export per-cell board statistics as CSV to this file
+    pub stats: Option<PathBuf>,
+}
+
+impl Args {
+    // Parse the given arguments (excluding the program name). Unknown arguments are logged and ignored.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Args {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--debug" => parsed.debug = true,
+                "--adaptive-render" => parsed.adaptive_render = true,
+                "--high-contrast" => parsed.high_contrast = true,
+                "--slow-motion" => parsed.slow_motion = true,
+                "--lenient-assets" => parsed.lenient_assets = true,
+                "--pause-on-focus-loss" => parsed.pause_on_focus_loss = true,
+                "--confirm-quit" => parsed.confirm_quit = true,
+                "--map" => parsed.map = path_for(&arg, &mut args),
+                "--lint-map" => parsed.lint_map = path_for(&arg, &mut args),
+                "--stats" => parsed.stats = path_for(&arg, &mut args),
+                _ => event!(tracing::Level::WARN, "Ignoring unknown argument: {}", arg),
+            }
+        }
+
+        if parsed.stats.is_some() && parsed.lint_map.is_none() {
+            event!(tracing::Level::WARN, "Ignoring --stats without --lint-map");
+        }
+
+        parsed
+    }
+
+    // Parse the arguments the process was started with
+    pub fn from_env() -> Args {
+        #[cfg(not(target_os = "emscripten"))]
+        {
+            Args::parse(std::env::args().skip(1))
+        }
+        #[cfg(target_os = "emscripten")]
+        {
+            Args::default()
+        }
+    }
+}
+
+// Takes the path following a flag, unless the next argument is another flag (which is left to be parsed)
+fn path_for<I: Iterator<Item = String>>(flag: &str, args: &mut Peekable<I>) -> Option<PathBuf> {
+    let path = args
+        .next_if(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from);
+    if path.is_none() {
+        event!(tracing::Level::WARN, "Missing path for {}", flag);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_debug() {
+        assert!(parse(&["--debug"]).debug);
+        assert!(!parse(&[]).debug);
+    }
+
+    #[test]
+    fn parses_map_path() {
+        let args = parse(&["--map", "board.txt"]);
+        assert_eq!(args.map, Some(PathBuf::from("board.txt")));
+    }
+
+    #[test]
+    fn flags_are_not_paths() {
+        let args = parse(&["--map", "--debug"]);
+        assert_eq!(args.map, None);
+        assert!(args.debug);
+
+        let args = parse(&["--lint-map", "board.txt", "--stats", "--debug"]);
+        assert_eq!(args.lint_map, Some(PathBuf::from("board.txt")));
+        assert_eq!(args.stats, None);
+        assert!(args.debug);
+    }
+}
@@ -7,15 +7,29 @@ pub const BOARD_OFFSET: (u32, u32) = (0, 3); // Relative cell offset for where m
 pub const WINDOW_WIDTH: u32 = CELL_SIZE * BOARD_WIDTH;
 pub const WINDOW_HEIGHT: u32 = CELL_SIZE * (BOARD_HEIGHT + 6); // Map texture is 6 cells taller (3 above, 3 below) than the grid
 
+// Fraction of normal game speed used in slow motion. SimpleTickModulator can skip at most every other tick, so this must
+// be at least 0.5 (lower values round to skipping every tick, freezing the game) and below 1.
+pub const SLOW_MOTION_SPEED: f32 = 0.5;
+const _: () = assert!(SLOW_MOTION_SPEED >= 0.5 && SLOW_MOTION_SPEED < 1.0);
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MapTile {
     Empty,
     Wall,
+    // The ghost house door; only ghosts may pass through it
+    Door,
     Pellet,
     PowerPellet,
     StartingPosition(u8),
 }
 
+impl MapTile {
+    // Whether Pac-Man can move into this tile
+    pub fn is_passable(&self) -> bool {
+        !matches!(self, MapTile::Wall | MapTile::Door)
+    }
+}
+
 pub const RAW_BOARD: [&str; BOARD_HEIGHT as usize] = [
     "############################",
     "#............##............#",
@@ -27,6 +27,41 @@ impl Direction {
         }
     }
 
+    // The direction after a 180 degree turn
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+        }
+    }
+
+    // The direction after a 90 degree counter-clockwise turn (as seen on screen)
+    pub fn turn_left(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+            Direction::Up => Direction::Left,
+        }
+    }
+
+    // The direction after a 90 degree clockwise turn (as seen on screen)
+    pub fn turn_right(&self) -> Direction {
+        match self {
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Up => Direction::Right,
+        }
+    }
+
+    // Both directions at a right angle to this one
+    pub fn perpendicular(&self) -> [Direction; 2] {
+        [self.turn_left(), self.turn_right()]
+    }
+
     pub fn from_keycode(keycode: Keycode) -> Option<Direction> {
         match keycode {
             Keycode::D => Some(Direction::Right),
@@ -41,3 +76,71 @@ impl Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    #[test]
+    fn opposite() {
+        assert_eq!(Direction::Up.opposite(), Direction::Down);
+        assert_eq!(Direction::Down.opposite(), Direction::Up);
+        assert_eq!(Direction::Left.opposite(), Direction::Right);
+        assert_eq!(Direction::Right.opposite(), Direction::Left);
+    }
+
+    #[test]
+    fn turn_left() {
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::Left.turn_left(), Direction::Down);
+        assert_eq!(Direction::Down.turn_left(), Direction::Right);
+        assert_eq!(Direction::Right.turn_left(), Direction::Up);
+    }
+
+    #[test]
+    fn turn_right() {
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Right.turn_right(), Direction::Down);
+        assert_eq!(Direction::Down.turn_right(), Direction::Left);
+        assert_eq!(Direction::Left.turn_right(), Direction::Up);
+    }
+
+    #[test]
+    fn perpendicular() {
+        assert_eq!(
+            Direction::Up.perpendicular(),
+            [Direction::Left, Direction::Right]
+        );
+        assert_eq!(
+            Direction::Down.perpendicular(),
+            [Direction::Right, Direction::Left]
+        );
+        assert_eq!(
+            Direction::Left.perpendicular(),
+            [Direction::Down, Direction::Up]
+        );
+        assert_eq!(
+            Direction::Right.perpendicular(),
+            [Direction::Up, Direction::Down]
+        );
+    }
+
+    #[test]
+    fn turns_are_consistent() {
+        for direction in ALL {
+            assert_eq!(direction.turn_left().turn_right(), direction);
+            assert_eq!(direction.turn_left().turn_left(), direction.opposite());
+            for perpendicular in direction.perpendicular() {
+                assert_ne!(perpendicular, direction);
+                assert_ne!(perpendicular, direction.opposite());
+            }
+        }
+    }
+}
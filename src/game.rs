@@ -1,63 +1,250 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 
 use sdl2::image::LoadTexture;
 use sdl2::keyboard::Keycode;
-use sdl2::render::{Texture, TextureCreator};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, TextureCreator};
+use sdl2::surface::Surface;
 use sdl2::video::WindowContext;
 use sdl2::{pixels::Color, render::Canvas, video::Window};
+use tracing::event;
 
-use crate::constants::{MapTile, BOARD_HEIGHT, BOARD_WIDTH, RAW_BOARD};
+use crate::args::Args;
+use crate::constants::{
+    MapTile, BOARD_HEIGHT, BOARD_WIDTH, CELL_SIZE, SLOW_MOTION_SPEED, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
 use crate::direction::Direction;
 use crate::entity::Entity;
 use crate::map::Map;
+use crate::modulation::{SimpleTickModulator, TickModulator};
 use crate::pacman::Pacman;
+use crate::pause::PauseState;
+
+/// Which debug overlays are drawn, cycled through with the debug key.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugView {
+    Off,
+    // Tiles colored by type
+    Tiles,
+    // Pac-Man's current and next cell
+    Movement,
+    All,
+}
+
+impl DebugView {
+    // The view after this one, wrapping back around to Off
+    pub fn next(self) -> DebugView {
+        match self {
+            DebugView::Off => DebugView::Tiles,
+            DebugView::Tiles => DebugView::Movement,
+            DebugView::Movement => DebugView::All,
+            DebugView::All => DebugView::Off,
+        }
+    }
+
+    fn shows_tiles(self) -> bool {
+        matches!(self, DebugView::Tiles | DebugView::All)
+    }
+
+    fn shows_movement(self) -> bool {
+        matches!(self, DebugView::Movement | DebugView::All)
+    }
+}
+
+/// Points scored and pellets eaten so far.
+#[derive(Debug, Default)]
+struct Score {
+    points: u32,
+    pellets: u32,
+}
+
+impl Score {
+    // Eats the pellet at the given cell, if any. Returns whether one was eaten.
+    fn eat(&mut self, map: &mut Map, cell: (i32, i32)) -> bool {
+        let points = map.eat(cell);
+        if points == 0 {
+            return false;
+        }
+
+        self.pellets += 1;
+        self.points += points;
+        true
+    }
+}
 
 pub struct Game<'a> {
     canvas: &'a mut Canvas<Window>,
     map_texture: Texture<'a>,
+    pellet_texture: Texture<'a>,
+    power_pellet_texture: Texture<'a>,
     pacman: Pacman<'a>,
-    map: Rc<Map>,
-    debug: bool,
+    map: Rc<RefCell<Map>>,
+    debug: DebugView,
+    // Draw pellets enlarged for visibility (rendering only, eating is unaffected)
+    high_contrast: bool,
+    // Skips game ticks to run the whole game slower, when slow motion is enabled
+    slow_motion: Option<SimpleTickModulator>,
+    // The cell currently under the mouse cursor (debug only)
+    hovered_cell: Option<(u32, u32)>,
+    // Number of simulation steps run per frame (debug only)
+    turbo: u32,
+    score: Score,
 }
 
 impl Game<'_> {
     pub fn new<'a>(
         canvas: &'a mut Canvas<Window>,
         texture_creator: &'a TextureCreator<WindowContext>,
+        map: Map,
+        args: &Args,
     ) -> Game<'a> {
-        let map = Rc::new(Map::new(RAW_BOARD));
-        let pacman_atlas = texture_creator
-            .load_texture("assets/32/pacman.png")
-            .expect("Could not load pacman texture");
-        let pacman = Pacman::new((1, 1), pacman_atlas, Rc::clone(&map));
+        let map = Rc::new(RefCell::new(map));
+        let load = |path, placeholder_size| {
+            load_texture(texture_creator, path, placeholder_size, args.lenient_assets)
+        };
+        let pacman_atlas = load("assets/32/pacman.png", (96, 32));
+        let start = map
+            .borrow()
+            .find_start()
+            .expect("Board has no Pac-Man starting position");
+        let pacman = Pacman::new(start, pacman_atlas, Rc::clone(&map));
 
         Game {
             canvas,
             pacman: pacman,
-            debug: false,
+            debug: if args.debug {
+                DebugView::All
+            } else {
+                DebugView::Off
+            },
+            high_contrast: args.high_contrast,
             map: map,
-            map_texture: texture_creator
-                .load_texture("assets/map.png")
-                .expect("Could not load pacman texture"),
+            map_texture: load("assets/map.png", (WINDOW_WIDTH, WINDOW_HEIGHT)),
+            pellet_texture: load("assets/24/pellet.png", (CELL_SIZE, CELL_SIZE)),
+            power_pellet_texture: load("assets/24/energizer.png", (CELL_SIZE, CELL_SIZE)),
+            slow_motion: args
+                .slow_motion
+                .then(|| SimpleTickModulator::new(SLOW_MOTION_SPEED)),
+            hovered_cell: None,
+            turbo: 1,
+            score: Score::default(),
         }
     }
 
     pub fn keyboard_event(&mut self, keycode: Keycode) {
         // Change direction
-        let direction = Direction::from_keycode(keycode);
-        self.pacman.next_direction = direction;
+        self.pacman.next_direction = requested_direction(keycode, self.pacman.controls_reversed);
 
-        // Toggle debug mode
+        // Cycle debug views
         if keycode == Keycode::Space {
-            self.debug = !self.debug;
+            self.debug = self.debug.next();
+            event!(tracing::Level::DEBUG, "Debug view: {:?}", self.debug);
+
+            // Debug tools don't outlive debug mode
+            if self.debug == DebugView::Off {
+                self.hovered_cell = None;
+            }
+        }
+
+        // Cycle turbo speed (debug only)
+        let turbo = next_turbo(self.debug, self.turbo, keycode);
+        if turbo != self.turbo {
+            self.turbo = turbo;
+            event!(tracing::Level::DEBUG, "Turbo set to {}x", self.turbo);
+        }
+
+        // Toggle high contrast pellets
+        if keycode == Keycode::H {
+            self.high_contrast = !self.high_contrast;
+        }
+
+        // Toggle slow motion
+        if keycode == Keycode::M {
+            self.slow_motion = match self.slow_motion {
+                Some(_) => None,
+                None => Some(SimpleTickModulator::new(SLOW_MOTION_SPEED)),
+            };
+            event!(
+                tracing::Level::INFO,
+                "Slow motion {}",
+                if self.slow_motion.is_some() {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+        }
+
+        // Toggle reversed controls (debug only)
+        if self.debug != DebugView::Off && keycode == Keycode::R {
+            self.pacman.controls_reversed = !self.pacman.controls_reversed;
+            event!(
+                tracing::Level::DEBUG,
+                "Reversed controls {}",
+                if self.pacman.controls_reversed {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+        }
+    }
+
+    // Tracks the cell under the mouse cursor, logging its tile when it changes (debug only)
+    pub fn mouse_motion(&mut self, position: (i32, i32)) {
+        if self.debug == DebugView::Off {
+            return;
+        }
+
+        let hovered_cell = Map::cell_at_pixel(position);
+        if hovered_cell != self.hovered_cell {
+            self.hovered_cell = hovered_cell;
+            if let Some((x, y)) = hovered_cell {
+                let tile = self
+                    .map
+                    .borrow()
+                    .get_tile((x as i32, y as i32))
+                    .unwrap_or(MapTile::Empty);
+                event!(tracing::Level::DEBUG, "Hovering {:?}: {:?}", (x, y), tile);
+            }
         }
     }
 
     pub fn tick(&mut self) {
-        self.pacman.tick();
+        for _ in 0..frame_steps(self.slow_motion.as_mut(), self.turbo) {
+            self.pacman.tick();
+            self.check_pellet_eaten();
+        }
+    }
+
+    // Eats the pellet under Pac-Man, if any
+    fn check_pellet_eaten(&mut self) {
+        let (x, y) = self.pacman.cell_position();
+        let cell = (x as i32, y as i32);
+
+        if self.score.eat(&mut self.map.borrow_mut(), cell) {
+            event!(
+                tracing::Level::DEBUG,
+                "Pellet eaten at {:?} [score={}] [pellets={}]",
+                cell,
+                self.score.points,
+                self.score.pellets
+            );
+        }
     }
 
     pub fn draw(&mut self) {
+        self.draw_frame(None);
+    }
+
+    // Draws the current frame without advancing any animations, dimmed if the pause calls for it
+    pub fn draw_paused(&mut self, pause: &PauseState) {
+        self.draw_frame(Some(pause));
+    }
+
+    fn draw_frame(&mut self, pause: Option<&PauseState>) {
         // Clear the screen (black)
         self.canvas.set_draw_color(Color::RGB(0, 0, 0));
         self.canvas.clear();
@@ -68,40 +255,76 @@ impl Game<'_> {
             .copy(&self.map_texture, None, None)
             .expect("Could not render texture on canvas");
 
+        // Render the pellets
+        for x in 0..BOARD_WIDTH {
+            for y in 0..BOARD_HEIGHT {
+                let texture = match self.map.borrow().get_tile((x as i32, y as i32)) {
+                    Some(MapTile::Pellet) => &self.pellet_texture,
+                    Some(MapTile::PowerPellet) => &self.power_pellet_texture,
+                    _ => continue,
+                };
+
+                self.canvas
+                    .copy(texture, None, Some(pellet_rect((x, y), self.high_contrast)))
+                    .expect("Could not render pellet on canvas");
+            }
+        }
+
         // Render the pacman
-        self.pacman.render(self.canvas);
+        if pause.is_some() {
+            self.pacman.render_frozen(self.canvas);
+        } else {
+            self.pacman.render(self.canvas);
+        }
 
-        // Draw a grid
-        if self.debug {
+        // Draw the tile grid
+        if self.debug.shows_tiles() {
             for x in 0..BOARD_WIDTH {
                 for y in 0..BOARD_HEIGHT {
                     let tile = self
                         .map
+                        .borrow()
                         .get_tile((x as i32, y as i32))
                         .unwrap_or(MapTile::Empty);
-                    let mut color = None;
-
-                    if (x, y) == self.pacman.cell_position() {
-                        self.draw_cell((x, y), Color::CYAN);
-                    } else {
-                        color = match tile {
-                            MapTile::Empty => None,
-                            MapTile::Wall => Some(Color::BLUE),
-                            MapTile::Pellet => Some(Color::RED),
-                            MapTile::PowerPellet => Some(Color::MAGENTA),
-                            MapTile::StartingPosition(_) => Some(Color::GREEN),
-                        };
-                    }
+                    let color = match tile {
+                        MapTile::Empty => None,
+                        MapTile::Wall => Some(Color::BLUE),
+                        MapTile::Door => Some(Color::GREY),
+                        MapTile::Pellet => Some(Color::RED),
+                        MapTile::PowerPellet => Some(Color::MAGENTA),
+                        MapTile::StartingPosition(_) => Some(Color::GREEN),
+                    };
 
                     if let Some(color) = color {
                         self.draw_cell((x, y), color);
                     }
                 }
             }
+        }
+
+        if self.debug.shows_movement() {
+            // Draw the current cell
+            self.draw_cell(self.pacman.cell_position(), Color::CYAN);
+
+            // Draw the next cell, unless it's off the board (e.g. past the end of the tunnel)
+            let next_cell =
+                Map::cell_in_direction(self.pacman.cell_position(), self.pacman.direction);
+            if let Some(next_cell) = next_cell {
+                self.draw_cell(next_cell, Color::YELLOW);
+            }
+        }
+
+        // Draw the cell under the mouse cursor
+        if let Some(cell) = self.hovered_cell {
+            self.draw_cell(cell, Color::WHITE);
+        }
 
-            // Draw the next cell
-            let next_cell = self.pacman.next_cell(None);
-            self.draw_cell((next_cell.0 as u32, next_cell.1 as u32), Color::YELLOW);
+        // Dim everything while paused
+        if pause.is_some_and(PauseState::dims) {
+            self.canvas.set_blend_mode(BlendMode::Blend);
+            self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+            self.canvas.fill_rect(None).expect("Could not dim canvas");
+            self.canvas.set_blend_mode(BlendMode::None);
         }
 
         // Present the canvas
@@ -121,3 +344,237 @@ impl Game<'_> {
             .expect("Could not draw rectangle");
     }
 }
+
+// Where to draw the pellet in a cell. High contrast pellets are drawn at 1.5x size, still centered on the cell.
+fn pellet_rect(cell: (u32, u32), high_contrast: bool) -> Rect {
+    let size = if high_contrast {
+        CELL_SIZE * 3 / 2
+    } else {
+        CELL_SIZE
+    };
+    let offset = (size as i32 - CELL_SIZE as i32) / 2;
+    let position = Map::cell_to_pixel(cell);
+
+    Rect::new(position.0 - offset, position.1 - offset, size, size)
+}
+
+// The turbo speed after a key press, given the (already updated) debug view. T cycles between 1x, 2x and 4x while a
+// debug view is shown, and turbo is reset to 1x when debug views are turned off.
+fn next_turbo(debug: DebugView, turbo: u32, keycode: Keycode) -> u32 {
+    match debug {
+        DebugView::Off => 1,
+        _ if keycode == Keycode::T => match turbo {
+            1 => 2,
+            2 => 4,
+            _ => 1,
+        },
+        _ => turbo,
+    }
+}
+
+// The number of simulation steps to run this frame. Slow motion skips some frames entirely.
+fn frame_steps(slow_motion: Option<&mut SimpleTickModulator>, turbo: u32) -> u32 {
+    if let Some(modulation) = slow_motion {
+        if !modulation.next() {
+            return 0;
+        }
+    }
+
+    turbo
+}
+
+// The direction a key asks Pac-Man to move in, inverted when the controls are reversed
+fn requested_direction(keycode: Keycode, reversed: bool) -> Option<Direction> {
+    let direction = Direction::from_keycode(keycode);
+    if reversed {
+        direction.map(|direction| direction.opposite())
+    } else {
+        direction
+    }
+}
+
+// Loads a texture from disk. If `lenient` is set, a texture that can't be loaded is replaced by a solid magenta
+// placeholder of the given size (rather than panicking), so missing assets are obvious but don't stop the game.
+fn load_texture<'a>(
+    texture_creator: &'a TextureCreator<WindowContext>,
+    path: &str,
+    placeholder_size: (u32, u32),
+    lenient: bool,
+) -> Texture<'a> {
+    let loaded = texture_creator.load_texture(path);
+    match texture_or_placeholder(loaded, path, placeholder_size, lenient) {
+        Ok(TextureSource::Asset(texture)) => texture,
+        Ok(TextureSource::Placeholder((width, height))) => {
+            let mut surface = Surface::new(width, height, PixelFormatEnum::RGB888)
+                .expect("Could not create placeholder surface");
+            surface
+                .fill_rect(None, Color::MAGENTA)
+                .expect("Could not fill placeholder surface");
+            texture_creator
+                .create_texture_from_surface(&surface)
+                .expect("Could not create placeholder texture")
+        }
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// What to draw for a texture: the loaded asset, or a placeholder of the given size.
+#[derive(Debug, PartialEq)]
+enum TextureSource<T> {
+    Asset(T),
+    Placeholder((u32, u32)),
+}
+
+// Decides what to draw for a texture that may have failed to load. Failures are errors, unless `lenient` is set.
+fn texture_or_placeholder<T>(
+    loaded: Result<T, String>,
+    path: &str,
+    placeholder_size: (u32, u32),
+    lenient: bool,
+) -> Result<TextureSource<T>, String> {
+    match loaded {
+        Ok(texture) => Ok(TextureSource::Asset(texture)),
+        Err(error) if lenient => {
+            event!(
+                tracing::Level::WARN,
+                "Could not load texture {}, using a placeholder: {}",
+                path,
+                error
+            );
+            Ok(TextureSource::Placeholder(placeholder_size))
+        }
+        Err(error) => Err(format!("Could not load texture {}: {}", path, error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::RAW_BOARD;
+    use crate::pacman::advance;
+
+    #[test]
+    fn reversed_controls_invert_direction() {
+        assert_eq!(requested_direction(Keycode::Up, false), Some(Direction::Up));
+        assert_eq!(
+            requested_direction(Keycode::Up, true),
+            Some(Direction::Down)
+        );
+        assert_eq!(
+            requested_direction(Keycode::A, true),
+            Some(Direction::Right)
+        );
+        assert_eq!(requested_direction(Keycode::Space, true), None);
+    }
+
+    #[test]
+    fn turbo_cycles_in_debug_only() {
+        assert_eq!(next_turbo(DebugView::All, 1, Keycode::T), 2);
+        assert_eq!(next_turbo(DebugView::All, 2, Keycode::T), 4);
+        assert_eq!(next_turbo(DebugView::All, 4, Keycode::T), 1);
+        assert_eq!(next_turbo(DebugView::Tiles, 4, Keycode::Up), 4);
+
+        assert_eq!(next_turbo(DebugView::Off, 1, Keycode::T), 1);
+        // Turning debug views off resets turbo
+        assert_eq!(next_turbo(DebugView::Off, 4, Keycode::Space), 1);
+    }
+
+    #[test]
+    fn turbo_moves_pacman_further() {
+        // How far Pac-Man moves over 10 frames
+        let travel = |turbo| {
+            let mut position = Map::cell_to_pixel((1, 1));
+            for _ in 0..10 {
+                for _ in 0..frame_steps(None, turbo) {
+                    position = advance(position, Direction::Right, 3);
+                }
+            }
+            position.0 - Map::cell_to_pixel((1, 1)).0
+        };
+
+        assert_eq!(travel(1), 30);
+        assert_eq!(travel(2), 2 * travel(1));
+        assert_eq!(travel(4), 4 * travel(1));
+    }
+
+    #[test]
+    fn slow_motion_halves_distance() {
+        // How far Pac-Man moves over 60 frames
+        let travel = |mut slow_motion: Option<SimpleTickModulator>| {
+            let mut position = Map::cell_to_pixel((1, 1));
+            for _ in 0..60 {
+                for _ in 0..frame_steps(slow_motion.as_mut(), 1) {
+                    position = advance(position, Direction::Right, 3);
+                }
+            }
+            position.0 - Map::cell_to_pixel((1, 1)).0
+        };
+
+        let normal = travel(None);
+        assert_eq!(normal, 180);
+        assert_eq!(
+            travel(Some(SimpleTickModulator::new(SLOW_MOTION_SPEED))),
+            normal / 2
+        );
+    }
+
+    #[test]
+    fn high_contrast_pellets_are_larger_and_centered() {
+        let normal = pellet_rect((5, 7), false);
+        let enlarged = pellet_rect((5, 7), true);
+
+        assert_eq!(normal.top_left(), Map::cell_to_pixel((5, 7)).into());
+        assert_eq!(normal.width(), CELL_SIZE);
+        assert!(enlarged.width() > normal.width());
+        assert!(enlarged.height() > normal.height());
+        assert_eq!(enlarged.center(), normal.center());
+    }
+
+    #[test]
+    fn missing_texture_fails_unless_lenient() {
+        let missing = || Err::<(), _>("Couldn't open assets/missing.png".to_string());
+
+        assert!(texture_or_placeholder(missing(), "assets/missing.png", (24, 24), false).is_err());
+        assert_eq!(
+            texture_or_placeholder(missing(), "assets/missing.png", (24, 24), true),
+            Ok(TextureSource::Placeholder((24, 24)))
+        );
+        assert_eq!(
+            texture_or_placeholder(Ok(()), "assets/24/pellet.png", (24, 24), false),
+            Ok(TextureSource::Asset(()))
+        );
+    }
+
+    #[test]
+    fn pellet_is_counted_once() {
+        let mut map = Map::new(RAW_BOARD);
+        let mut score = Score::default();
+
+        // Pac-Man spends several ticks on each cell
+        for _ in 0..8 {
+            score.eat(&mut map, (1, 1));
+        }
+        assert_eq!(score.pellets, 1);
+        assert_eq!(score.points, 10);
+
+        for _ in 0..8 {
+            score.eat(&mut map, (1, 3));
+        }
+        assert_eq!(score.pellets, 2);
+        assert_eq!(score.points, 60);
+    }
+
+    #[test]
+    fn debug_view_cycles() {
+        let views = [
+            DebugView::Off,
+            DebugView::Tiles,
+            DebugView::Movement,
+            DebugView::All,
+        ];
+
+        for (i, view) in views.iter().enumerate() {
+            assert_eq!(view.next(), views[(i + 1) % views.len()]);
+        }
+    }
+}
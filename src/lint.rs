@@ -0,0 +1,187 @@
+use std::fmt;
+use std::fmt::Write;
+
+use crate::constants::{MapTile, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::map::Map;
+
+// Starting position markers: 0 is Pac-Man, 1 through 4 are the ghosts
+const STARTING_POSITIONS: u8 = 5;
+
+/// A problem with a board that would make it unplayable.
+#[derive(Debug)]
+pub enum LintIssue {
+    // A ghost's starting position marker does not appear on the board
+    MissingStart(u8),
+    // Pac-Man's starting position marker appears more than once
+    DuplicateStart(usize),
+    // The board has no pellets or power pellets to eat
+    NoPellets,
+    // Pellets that can't be reached from Pac-Man's starting position
+    UnreachablePellets(usize),
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::MissingStart(marker) => {
+                write!(f, "Missing starting position '{}'", marker)
+            }
+            LintIssue::DuplicateStart(count) => {
+                write!(f, "Starting position '0' appears {} times", count)
+            }
+            LintIssue::NoPellets => write!(f, "Board has no pellets"),
+            LintIssue::UnreachablePellets(count) => write!(
+                f,
+                "{} pellets can't be reached from Pac-Man's starting position",
+                count
+            ),
+        }
+    }
+}
+
+// Checks a board for missing markers and unreachable pellets. Returns an empty list if the board is playable.
+pub fn lint(map: &Map) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let components = map.connected_components();
+    let tile = |(x, y): (u32, u32)| map.get_tile((x as i32, y as i32));
+    let is_pellet = |cell| matches!(tile(cell), Some(MapTile::Pellet | MapTile::PowerPellet));
+
+    let count = |marker| {
+        components
+            .iter()
+            .flatten()
+            .filter(|&&cell| tile(cell) == Some(MapTile::StartingPosition(marker)))
+            .count()
+    };
+
+    // Parsing guarantees Pac-Man has a starting position and that there are exactly as many ghost starting positions
+    // as ghosts, so a missing ghost marker means another one is duplicated
+    let pacman_starts = count(0);
+    if pacman_starts > 1 {
+        issues.push(LintIssue::DuplicateStart(pacman_starts));
+    }
+    for marker in 1..STARTING_POSITIONS {
+        if count(marker) == 0 {
+            issues.push(LintIssue::MissingStart(marker));
+        }
+    }
+
+    let pellets = components
+        .iter()
+        .flatten()
+        .filter(|&&cell| is_pellet(cell))
+        .count();
+    if pellets == 0 {
+        issues.push(LintIssue::NoPellets);
+    }
+
+    // Reachability is measured from Pac-Man's starting position (the first one, if duplicated)
+    let start = components.iter().find(|component| {
+        component
+            .iter()
+            .any(|&cell| tile(cell) == Some(MapTile::StartingPosition(0)))
+    });
+    if let Some(start) = start {
+        let reachable = start.iter().filter(|&&cell| is_pellet(cell)).count();
+        if reachable < pellets {
+            issues.push(LintIssue::UnreachablePellets(pellets - reachable));
+        }
+    }
+
+    issues
+}
+
+// Exports every cell of the board as CSV, for balancing pellet placement on custom boards. Each row holds the
+// cell's position, its tile (as written in the board format) and its walking distance from the ghost house exit
+// (Blinky's starting position), which is left blank if the cell is unreachable or the board has no exit marker.
+pub fn board_stats_csv(map: &Map) -> String {
+    let cells = || (0..BOARD_HEIGHT).flat_map(|y| (0..BOARD_WIDTH).map(move |x| (x, y)));
+    let tile = |(x, y): (u32, u32)| map.get_tile((x as i32, y as i32));
+
+    let house_exit = cells().find(|&cell| tile(cell) == Some(MapTile::StartingPosition(1)));
+    let distances = house_exit.map(|exit| map.distances_from(exit));
+
+    let mut csv = String::from("x,y,tile,house_distance\n");
+    for (x, y) in cells() {
+        let symbol = match tile((x, y)) {
+            Some(MapTile::Wall) => "#".to_string(),
+            Some(MapTile::Door) => "=".to_string(),
+            Some(MapTile::Pellet) => ".".to_string(),
+            Some(MapTile::PowerPellet) => "o".to_string(),
+            Some(MapTile::StartingPosition(marker)) => marker.to_string(),
+            Some(MapTile::Empty) | None => " ".to_string(),
+        };
+        let distance = distances
+            .and_then(|distances| distances[x as usize][y as usize])
+            .map(|distance| distance.to_string())
+            .unwrap_or_default();
+
+        writeln!(csv, "{},{},\"{}\",{}", x, y, symbol, distance).unwrap();
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::RAW_BOARD;
+
+    #[test]
+    fn raw_board_has_no_issues() {
+        assert!(lint(&Map::new(RAW_BOARD)).is_empty());
+    }
+
+    #[test]
+    fn reports_no_pellets() {
+        let mut map = Map::new(RAW_BOARD);
+        for x in 0..BOARD_WIDTH as i32 {
+            for y in 0..BOARD_HEIGHT as i32 {
+                if matches!(
+                    map.get_tile((x, y)),
+                    Some(MapTile::Pellet | MapTile::PowerPellet)
+                ) {
+                    map.set_tile((x, y), MapTile::Empty);
+                }
+            }
+        }
+
+        let issues = lint(&map);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0], LintIssue::NoPellets));
+    }
+
+    #[test]
+    fn reports_unreachable_pellets() {
+        // The dead space beside the ghost house is walled off from the playfield
+        let mut map = Map::new(RAW_BOARD);
+        map.set_tile((0, 10), MapTile::Pellet);
+
+        let issues = lint(&map);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0], LintIssue::UnreachablePellets(1)));
+    }
+
+    #[test]
+    fn stats_cover_every_cell() {
+        let csv = board_stats_csv(&Map::new(RAW_BOARD));
+        let rows: Vec<&str> = csv.lines().skip(1).collect();
+
+        assert_eq!(rows.len(), (BOARD_WIDTH * BOARD_HEIGHT) as usize);
+        let pellets = rows
+            .iter()
+            .filter(|row| row.contains("\".\"") || row.contains("\"o\""))
+            .count();
+        assert_eq!(pellets, 244);
+    }
+
+    #[test]
+    fn reports_misplaced_ghost_start() {
+        let mut map = Map::new(RAW_BOARD);
+        map.set_tile((15, 14), MapTile::StartingPosition(3));
+
+        let issues = lint(&map);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0], LintIssue::MissingStart(4)));
+    }
+}
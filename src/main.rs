@@ -1,5 +1,9 @@
-use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::args::Args;
+use crate::constants::{RAW_BOARD, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::game::Game;
+use crate::map::Map;
+use crate::pause::{PauseState, QuitConfirm};
+use crate::timing::{FpsCounter, LogLimiter, RenderSkip};
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use std::time::{Duration, Instant};
@@ -8,18 +12,19 @@ use tracing_error::ErrorLayer;
 use tracing_subscriber::layer::SubscriberExt;
 
 mod animation;
+mod args;
 mod constants;
 mod direction;
 mod entity;
 mod game;
+mod lint;
 mod map;
 mod modulation;
 mod pacman;
+mod pause;
+mod timing;
 
 pub fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-
     // Setup tracing
     let subscriber = tracing_subscriber::fmt()
         .with_ansi(cfg!(not(target_os = "emscripten")))
@@ -29,6 +34,62 @@ pub fn main() {
 
     tracing::subscriber::set_global_default(subscriber).expect("Could not set global default");
 
+    let args = Args::from_env();
+
+    // Lint a board and exit without opening a window
+    if let Some(path) = &args.lint_map {
+        let issues: Vec<String> = match Map::from_file(path) {
+            Ok(map) => {
+                if let Some(stats_path) = &args.stats {
+                    if let Err(error) = std::fs::write(stats_path, lint::board_stats_csv(&map)) {
+                        println!(
+                            "Could not write board statistics to {}: {}",
+                            stats_path.display(),
+                            error
+                        );
+                        std::process::exit(1);
+                    }
+                    println!("Board statistics written to {}", stats_path.display());
+                }
+
+                lint::lint(&map).iter().map(ToString::to_string).collect()
+            }
+            Err(error) => vec![error.to_string()],
+        };
+
+        if issues.is_empty() {
+            println!("{}: OK", path.display());
+            return;
+        }
+
+        println!("{}: {} problem(s)", path.display(), issues.len());
+        for issue in issues {
+            println!("  - {}", issue);
+        }
+        std::process::exit(1);
+    }
+
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+
+    let map = match &args.map {
+        Some(path) => Map::from_file(path).unwrap_or_else(|error| {
+            event!(
+                tracing::Level::WARN,
+                "Could not load map from {}, using the default board: {}",
+                path.display(),
+                error
+            );
+            Map::new(RAW_BOARD)
+        }),
+        None => Map::new(RAW_BOARD),
+    };
+
+    // Custom boards are still playable with issues, but are likely a mistake
+    for issue in lint::lint(&map) {
+        event!(tracing::Level::WARN, "Board issue: {}", issue);
+    }
+
     let window = video_subsystem
         .window("Pac-Man", WINDOW_WIDTH, WINDOW_HEIGHT)
         .position_centered()
@@ -45,7 +106,7 @@ pub fn main() {
         .expect("Could not set logical size");
 
     let texture_creator = canvas.texture_creator();
-    let mut game = Game::new(&mut canvas, &texture_creator);
+    let mut game = Game::new(&mut canvas, &texture_creator, map, &args);
 
     let mut event_pump = sdl_context
         .event_pump()
@@ -61,8 +122,13 @@ pub fn main() {
     // The start of a period of time over which we average the frame time.
     let mut last_averaging_time = Instant::now();
     let mut sleep_time = Duration::ZERO;
-    let mut paused = false;
+    let mut pause = PauseState::default();
     let mut shown = false;
+    let mut render_skip = args.adaptive_render.then(|| RenderSkip::new(loop_time));
+    let mut fps_counter = FpsCounter::default();
+    let mut slow_frame_warnings = LogLimiter::new(Duration::from_secs(1));
+    let mut slow_frames = 0u32;
+    let mut quit_confirm = QuitConfirm::new(Duration::from_secs(3));
 
     event!(
         tracing::Level::INFO,
@@ -74,6 +140,20 @@ pub fn main() {
 
         // TODO: Fix key repeat delay issues by using VecDeque for instant key repeat
         for event in event_pump.poll_iter() {
+            let quit_pending = quit_confirm.pending(Instant::now());
+
+            // Any other key cancels a pending quit
+            if let Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } = event
+            {
+                if quit_pending && !matches!(keycode, Keycode::Escape | Keycode::Q | Keycode::Y) {
+                    quit_confirm.cancel();
+                    event!(tracing::Level::INFO, "Quit cancelled");
+                }
+            }
+
             match event {
                 Event::Window { win_event, .. } => match win_event {
                     WindowEvent::Hidden => {
@@ -84,22 +164,53 @@ pub fn main() {
                         event!(tracing::Level::DEBUG, "Window shown");
                         shown = true;
                     }
+                    WindowEvent::FocusLost => {
+                        event!(tracing::Level::DEBUG, "Window focus lost");
+                        if args.pause_on_focus_loss && pause.focus_lost() {
+                            event!(tracing::Level::INFO, "Paused (focus lost)");
+                        }
+                    }
+                    WindowEvent::FocusGained => {
+                        event!(tracing::Level::DEBUG, "Window focus gained");
+                        if pause.focus_gained() {
+                            event!(tracing::Level::INFO, "Unpaused (focus gained)");
+                        }
+                    }
                     _ => {}
                 },
-                // Handle quitting keys or window close
-                Event::Quit { .. }
-                | Event::KeyDown {
+                // Handle window close
+                Event::Quit { .. } => {
+                    event!(tracing::Level::INFO, "Exit requested. Exiting...");
+                    return false;
+                }
+                // Handle quitting keys, asking for confirmation first if enabled
+                Event::KeyDown {
                     keycode: Some(Keycode::Escape) | Some(Keycode::Q),
+                    repeat,
                     ..
                 } => {
-                    event!(tracing::Level::INFO, "Exit requested. Exiting...");
+                    if !args.confirm_quit || quit_confirm.request(Instant::now(), repeat) {
+                        event!(tracing::Level::INFO, "Exit requested. Exiting...");
+                        return false;
+                    } else if !quit_pending {
+                        event!(
+                            tracing::Level::INFO,
+                            "Quit? Press Escape or Q again (or Y) to confirm"
+                        );
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Y),
+                    ..
+                } if quit_confirm.confirm(Instant::now()) => {
+                    event!(tracing::Level::INFO, "Exit confirmed. Exiting...");
                     return false;
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::P),
                     ..
                 } => {
-                    paused = !paused;
+                    let paused = pause.toggle();
                     event!(
                         tracing::Level::INFO,
                         "{}",
@@ -109,14 +220,28 @@ pub fn main() {
                 Event::KeyDown { keycode, .. } => {
                     game.keyboard_event(keycode.unwrap());
                 }
+                Event::MouseMotion { x, y, .. } => {
+                    game.mouse_motion((x, y));
+                }
                 _ => {}
             }
         }
 
         // TODO: Proper pausing implementation that does not interfere with statistic gathering
-        if !paused {
+        if !pause.is_paused() {
             game.tick();
-            game.draw();
+
+            match &mut render_skip {
+                Some(render_skip) => {
+                    if render_skip.should_render() {
+                        game.draw();
+                        render_skip.record(start.elapsed());
+                    }
+                }
+                None => game.draw(),
+            }
+        } else {
+            game.draw_paused(&pause);
         }
 
         if start.elapsed() < loop_time {
@@ -133,14 +258,27 @@ pub fn main() {
             }
             sleep_time += time;
         } else {
+            let behind = start.elapsed() - loop_time;
+            slow_frames += 1;
+
+            // Slow frames tend to come in runs, so only warn occasionally
             event!(
-                tracing::Level::WARN,
+                tracing::Level::TRACE,
                 "Game loop behind schedule by: {:?}",
-                start.elapsed() - loop_time
+                behind
             );
+            if let Some(suppressed) = slow_frame_warnings.allow(Instant::now()) {
+                event!(
+                    tracing::Level::WARN,
+                    "Game loop behind schedule by: {:?} ({} slow frames since last warning)",
+                    behind,
+                    suppressed
+                );
+            }
         }
 
         tick_no += 1;
+        fps_counter.record(Instant::now());
 
         const PERIOD: u32 = 60 * 60;
         let tick_mod = tick_no % PERIOD;
@@ -148,16 +286,21 @@ pub fn main() {
             let average_fps = PERIOD as f32 / last_averaging_time.elapsed().as_secs_f32();
             let average_sleep = sleep_time / PERIOD;
             let average_process = loop_time - average_sleep;
+            let (fps_1s, fps_10s) = fps_counter.fps();
 
             event!(
                 tracing::Level::DEBUG,
-                "Timing Averages [fps={}] [sleep={:?}] [process={:?}]",
+                "Timing Averages [fps={}] [fps_1s={:.1}] [fps_10s={:.1}] [sleep={:?}] [process={:?}] [slow_frames={}]",
                 average_fps,
+                fps_1s,
+                fps_10s,
                 average_sleep,
-                average_process
+                average_process,
+                slow_frames
             );
 
             sleep_time = Duration::ZERO;
+            slow_frames = 0;
             last_averaging_time = Instant::now();
         }
 
@@ -1,5 +1,93 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+
 use crate::constants::MapTile;
-use crate::constants::{BOARD_HEIGHT, BOARD_WIDTH};
+use crate::constants::{BOARD_HEIGHT, BOARD_OFFSET, BOARD_WIDTH, CELL_SIZE};
+use crate::direction::Direction;
+
+// Number of ghost starting positions ('1' to '4') a board must have
+const GHOST_COUNT: usize = 4;
+
+#[derive(Debug)]
+pub enum MapError {
+    // The board file could not be read
+    Io(std::io::Error),
+    // The board does not have exactly BOARD_HEIGHT rows
+    WrongHeight(usize),
+    // A row of the board is not exactly BOARD_WIDTH characters wide
+    WrongWidth {
+        row: usize,
+        width: usize,
+    },
+    // A character in the board does not map to any tile, with its cell and the row it appears in
+    UnknownCharacter {
+        character: char,
+        position: (usize, usize),
+        line: String,
+    },
+    // The board has no Pac-Man starting position ('0')
+    MissingPacmanStart,
+    // The board does not have exactly one starting position per ghost ('1' to '4')
+    WrongGhostStartCount(usize),
+    // A row is open on one edge of the board but not the other, so the tunnel would lead into a wall
+    UnmatchedTunnel(usize),
+    // A cell in the top or bottom row is open, so Pac-Man could walk off the board
+    OpenEdge((usize, usize)),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(error) => write!(f, "Could not read board: {}", error),
+            MapError::WrongHeight(rows) => {
+                write!(f, "Board has {} rows, expected {}", rows, BOARD_HEIGHT)
+            }
+            MapError::WrongWidth { row, width } => write!(
+                f,
+                "Board row {} is {} characters wide, expected {}",
+                row, width, BOARD_WIDTH
+            ),
+            MapError::UnknownCharacter {
+                character,
+                position,
+                line,
+            } => write!(
+                f,
+                "Unknown character {:?} at {:?}: {:?}",
+                character, position, line
+            ),
+            MapError::MissingPacmanStart => write!(f, "Board has no Pac-Man starting position"),
+            MapError::WrongGhostStartCount(count) => write!(
+                f,
+                "Board has {} ghost starting positions, expected {}",
+                count, GHOST_COUNT
+            ),
+            MapError::UnmatchedTunnel(row) => {
+                write!(f, "Board row {} is only open on one side", row)
+            }
+            MapError::OpenEdge(position) => {
+                write!(f, "Board edge is open at {:?}", position)
+            }
+        }
+    }
+}
+
+// Checks that a raw board has exactly BOARD_HEIGHT rows of BOARD_WIDTH characters each
+pub fn validate_board(raw_board: &[&str]) -> Result<(), MapError> {
+    if raw_board.len() != BOARD_HEIGHT as usize {
+        return Err(MapError::WrongHeight(raw_board.len()));
+    }
+
+    for (row, line) in raw_board.iter().enumerate() {
+        let width = line.chars().count();
+        if width != BOARD_WIDTH as usize {
+            return Err(MapError::WrongWidth { row, width });
+        }
+    }
+
+    Ok(())
+}
 
 pub struct Map {
     inner: [[MapTile; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize],
@@ -7,22 +95,23 @@ pub struct Map {
 
 impl Map {
     pub fn new(raw_board: [&str; BOARD_HEIGHT as usize]) -> Map {
-        let mut inner = [[MapTile::Empty; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize];
+        Map::parse(&raw_board).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        for y in 0..BOARD_HEIGHT as usize {
-            let line = raw_board[y];
+    // Loads a board from a text file, using the same format as RAW_BOARD (one row per line)
+    pub fn from_file(path: &Path) -> Result<Map, MapError> {
+        let contents = std::fs::read_to_string(path).map_err(MapError::Io)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        Map::parse(&lines)
+    }
 
-            for x in 0..BOARD_WIDTH as usize {
-                if x >= line.len() {
-                    break;
-                }
+    pub fn parse(raw_board: &[&str]) -> Result<Map, MapError> {
+        validate_board(raw_board)?;
 
-                let i = (y * (BOARD_WIDTH as usize) + x) as usize;
-                let character = line
-                    .chars()
-                    .nth(x as usize)
-                    .unwrap_or_else(|| panic!("Could not get character at {} = ({}, {})", i, x, y));
+        let mut inner = [[MapTile::Empty; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize];
 
+        for (y, line) in raw_board.iter().enumerate() {
+            for (x, character) in line.chars().enumerate() {
                 let tile = match character {
                     '#' => MapTile::Wall,
                     '.' => MapTile::Pellet,
@@ -31,15 +120,57 @@ impl Map {
                     c @ '0' | c @ '1' | c @ '2' | c @ '3' | c @ '4' => {
                         MapTile::StartingPosition(c.to_digit(10).unwrap() as u8)
                     }
-                    '=' => MapTile::Empty,
-                    _ => panic!("Unknown character in board: {}", character),
+                    '=' => MapTile::Door,
+                    _ => {
+                        return Err(MapError::UnknownCharacter {
+                            character,
+                            position: (x, y),
+                            line: line.to_string(),
+                        })
+                    }
                 };
 
-                inner[x as usize][y as usize] = tile;
+                inner[x][y] = tile;
+            }
+        }
+
+        let map = Map { inner };
+        map.validate_layout()?;
+        Ok(map)
+    }
+
+    // Checks the parts of the layout the game relies on: the starting positions and the board's edges
+    fn validate_layout(&self) -> Result<(), MapError> {
+        let tiles = || self.inner.iter().flatten();
+
+        if self.find_start().is_none() {
+            return Err(MapError::MissingPacmanStart);
+        }
+
+        let ghost_starts = tiles()
+            .filter(|tile| matches!(tile, MapTile::StartingPosition(1..)))
+            .count();
+        if ghost_starts != GHOST_COUNT {
+            return Err(MapError::WrongGhostStartCount(ghost_starts));
+        }
+
+        let last = BOARD_WIDTH as usize - 1;
+        for row in 0..BOARD_HEIGHT as usize {
+            if self.inner[0][row].is_passable() != self.inner[last][row].is_passable() {
+                return Err(MapError::UnmatchedTunnel(row));
+            }
+        }
+
+        // Tunnels only wrap horizontally
+        for row in [0, BOARD_HEIGHT as usize - 1] {
+            for x in 0..BOARD_WIDTH as usize {
+                if self.inner[x][row].is_passable() {
+                    return Err(MapError::OpenEdge((x, row)));
+                }
             }
         }
 
-        Map { inner: inner }
+        Ok(())
     }
 
     pub fn get_tile(&self, cell: (i32, i32)) -> Option<MapTile> {
@@ -53,7 +184,325 @@ impl Map {
         Some(self.inner[x][y])
     }
 
+    // The cell holding Pac-Man's starting position ('0'), if the board has one
+    pub fn find_start(&self) -> Option<(u32, u32)> {
+        (0..BOARD_WIDTH)
+            .flat_map(|x| (0..BOARD_HEIGHT).map(move |y| (x, y)))
+            .find(|&(x, y)| self.inner[x as usize][y as usize] == MapTile::StartingPosition(0))
+    }
+
+    // Replaces the tile at the given cell. Returns false if the cell is outside the board.
+    pub fn set_tile(&mut self, cell: (i32, i32), tile: MapTile) -> bool {
+        let x = cell.0 as usize;
+        let y = cell.1 as usize;
+
+        if x >= BOARD_WIDTH as usize || y >= BOARD_HEIGHT as usize {
+            return false;
+        }
+
+        self.inner[x][y] = tile;
+        true
+    }
+
+    // Eats the pellet at the given cell, clearing the tile so it is only counted once. Returns the points it was worth,
+    // or 0 if there was nothing to eat.
+    pub fn eat(&mut self, cell: (i32, i32)) -> u32 {
+        let points = match self.get_tile(cell) {
+            Some(MapTile::Pellet) => 10,
+            Some(MapTile::PowerPellet) => 50,
+            _ => return 0,
+        };
+
+        self.set_tile(cell, MapTile::Empty);
+        points
+    }
+
+    // Groups the cells Pac-Man can move through into regions reachable from one another, including through the tunnel.
+    // Each region lists its cells in the order they were discovered.
+    pub fn connected_components(&self) -> Vec<Vec<(u32, u32)>> {
+        let passable = |(x, y): (u32, u32)| self.inner[x as usize][y as usize].is_passable();
+        let mut visited = [[false; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize];
+        let mut components = Vec::new();
+
+        for x in 0..BOARD_WIDTH {
+            for y in 0..BOARD_HEIGHT {
+                if visited[x as usize][y as usize] || !passable((x, y)) {
+                    continue;
+                }
+
+                let mut component = Vec::new();
+                let mut queue = VecDeque::from([(x, y)]);
+                visited[x as usize][y as usize] = true;
+
+                while let Some(cell) = queue.pop_front() {
+                    component.push(cell);
+
+                    for (nx, ny) in Map::neighbours(cell) {
+                        if !visited[nx as usize][ny as usize] && passable((nx, ny)) {
+                            visited[nx as usize][ny as usize] = true;
+                            queue.push_back((nx, ny));
+                        }
+                    }
+                }
+
+                components.push(component);
+            }
+        }
+
+        components
+    }
+
+    // The shortest walking distance (in cells) from the given cell to every cell Pac-Man can reach from it
+    pub fn distances_from(
+        &self,
+        start: (u32, u32),
+    ) -> [[Option<u32>; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize] {
+        let mut distances = [[None; BOARD_HEIGHT as usize]; BOARD_WIDTH as usize];
+        distances[start.0 as usize][start.1 as usize] = Some(0);
+        let mut queue = VecDeque::from([(start, 0)]);
+
+        while let Some((cell, distance)) = queue.pop_front() {
+            for (nx, ny) in Map::neighbours(cell) {
+                if distances[nx as usize][ny as usize].is_none()
+                    && self.inner[nx as usize][ny as usize].is_passable()
+                {
+                    distances[nx as usize][ny as usize] = Some(distance + 1);
+                    queue.push_back(((nx, ny), distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    // The cells adjacent to the given cell, wrapping horizontally like the tunnel does
+    fn neighbours(cell: (u32, u32)) -> impl Iterator<Item = (u32, u32)> {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .into_iter()
+        .filter_map(move |direction| {
+            let (dx, dy) = direction.offset();
+            let x = (cell.0 as i32 + dx).rem_euclid(BOARD_WIDTH as i32);
+            let y = cell.1 as i32 + dy;
+            (0..BOARD_HEIGHT as i32)
+                .contains(&y)
+                .then_some((x as u32, y as u32))
+        })
+    }
+
+    // The pixel position of a cell's top-left corner
     pub fn cell_to_pixel(cell: (u32, u32)) -> (i32, i32) {
-        ((cell.0 as i32) * 24, ((cell.1 + 3) as i32) * 24)
+        (
+            ((cell.0 + BOARD_OFFSET.0) * CELL_SIZE) as i32,
+            ((cell.1 + BOARD_OFFSET.1) * CELL_SIZE) as i32,
+        )
+    }
+
+    // The cell containing a pixel position. Positions outside the board give cells outside the board (possibly negative).
+    pub fn pixel_to_cell(pixel: (i32, i32)) -> (i32, i32) {
+        (
+            pixel.0.div_euclid(CELL_SIZE as i32) - BOARD_OFFSET.0 as i32,
+            pixel.1.div_euclid(CELL_SIZE as i32) - BOARD_OFFSET.1 as i32,
+        )
+    }
+
+    // The cell containing a pixel position, if it is on the board
+    pub fn cell_at_pixel(pixel: (i32, i32)) -> Option<(u32, u32)> {
+        Map::on_board(Map::pixel_to_cell(pixel))
+    }
+
+    // The adjacent cell in the given direction, if it is on the board (cells past the end of the tunnel are not)
+    pub fn cell_in_direction(cell: (u32, u32), direction: Direction) -> Option<(u32, u32)> {
+        let (dx, dy) = direction.offset();
+        Map::on_board((cell.0 as i32 + dx, cell.1 as i32 + dy))
+    }
+
+    // The cell, if it is on the board
+    fn on_board(cell: (i32, i32)) -> Option<(u32, u32)> {
+        let on_board =
+            (0..BOARD_WIDTH as i32).contains(&cell.0) && (0..BOARD_HEIGHT as i32).contains(&cell.1);
+        on_board.then_some((cell.0 as u32, cell.1 as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::RAW_BOARD;
+
+    // RAW_BOARD with the given cells replaced
+    fn board_with(changes: &[((usize, usize), char)]) -> Vec<String> {
+        let mut board: Vec<Vec<char>> = RAW_BOARD
+            .iter()
+            .map(|line| line.chars().collect())
+            .collect();
+        for &((x, y), character) in changes {
+            board[y][x] = character;
+        }
+        board.into_iter().map(String::from_iter).collect()
+    }
+
+    fn parse(board: &[String]) -> Result<Map, MapError> {
+        let lines: Vec<&str> = board.iter().map(String::as_str).collect();
+        Map::parse(&lines)
+    }
+
+    #[test]
+    fn accepts_raw_board() {
+        assert!(validate_board(&RAW_BOARD).is_ok());
+    }
+
+    #[test]
+    fn rejects_short_row() {
+        let mut board = RAW_BOARD;
+        board[4] = "#.####.#####.##.#####.####";
+
+        assert!(matches!(
+            validate_board(&board),
+            Err(MapError::WrongWidth { row: 4, width: 26 })
+        ));
+    }
+
+    #[test]
+    fn finds_pacman_start() {
+        assert_eq!(Map::new(RAW_BOARD).find_start(), Some((13, 23)));
+    }
+
+    #[test]
+    fn locates_unknown_characters() {
+        let board = board_with(&[((3, 5), 'x')]);
+        match parse(&board) {
+            Err(MapError::UnknownCharacter {
+                character,
+                position,
+                line,
+            }) => {
+                assert_eq!(character, 'x');
+                assert_eq!(position, (3, 5));
+                assert_eq!(line, board[5]);
+            }
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn rejects_missing_pacman_start() {
+        let board = board_with(&[((13, 23), ' ')]);
+        assert!(matches!(parse(&board), Err(MapError::MissingPacmanStart)));
+    }
+
+    #[test]
+    fn rejects_wrong_ghost_start_count() {
+        let board = board_with(&[((15, 14), ' ')]);
+        assert!(matches!(
+            parse(&board),
+            Err(MapError::WrongGhostStartCount(3))
+        ));
+    }
+
+    #[test]
+    fn rejects_unmatched_tunnel() {
+        let board = board_with(&[((0, 14), '#')]);
+        assert!(matches!(parse(&board), Err(MapError::UnmatchedTunnel(14))));
+    }
+
+    #[test]
+    fn rejects_open_edge() {
+        let board = board_with(&[((5, 30), '.')]);
+        assert!(matches!(parse(&board), Err(MapError::OpenEdge((5, 30)))));
+    }
+
+    #[test]
+    fn cell_at_pixel_is_only_on_board() {
+        assert_eq!(Map::cell_at_pixel((130, 250)), Some((5, 7)));
+        assert_eq!(Map::cell_at_pixel((0, 72)), Some((0, 0)));
+        // Above the board (over the score area), and past its right edge
+        assert_eq!(Map::cell_at_pixel((130, 10)), None);
+        assert_eq!(
+            Map::cell_at_pixel(((BOARD_WIDTH * CELL_SIZE) as i32, 250)),
+            None
+        );
+        assert_eq!(Map::cell_at_pixel((-1, 250)), None);
+    }
+
+    #[test]
+    fn cell_in_direction_stops_at_edges() {
+        assert_eq!(
+            Map::cell_in_direction((5, 7), Direction::Right),
+            Some((6, 7))
+        );
+        assert_eq!(Map::cell_in_direction((5, 7), Direction::Up), Some((5, 6)));
+        // Facing out of the tunnel
+        assert_eq!(Map::cell_in_direction((0, 14), Direction::Left), None);
+        assert_eq!(Map::cell_in_direction((27, 14), Direction::Right), None);
+        assert_eq!(Map::cell_in_direction((5, 0), Direction::Up), None);
+    }
+
+    #[test]
+    fn pellets_are_eaten_once() {
+        let mut map = Map::new(RAW_BOARD);
+
+        assert_eq!(map.eat((1, 1)), 10);
+        assert_eq!(map.get_tile((1, 1)), Some(MapTile::Empty));
+        assert_eq!(map.eat((1, 1)), 0);
+
+        assert_eq!(map.eat((1, 3)), 50);
+        assert_eq!(map.eat((1, 3)), 0);
+
+        // Walls and cells off the board are never eaten
+        assert_eq!(map.eat((0, 0)), 0);
+        assert_eq!(map.get_tile((0, 0)), Some(MapTile::Wall));
+        assert_eq!(map.eat((-1, 14)), 0);
+    }
+
+    #[test]
+    fn pixel_cell_round_trip() {
+        let pixel = Map::cell_to_pixel((5, 7));
+        assert_eq!(pixel, (120, 240));
+        assert_eq!(Map::pixel_to_cell(pixel), (5, 7));
+        assert_eq!(Map::pixel_to_cell((pixel.0 + 23, pixel.1 + 23)), (5, 7));
+
+        // Pixels left of or above the board give negative cells, and past the right edge give cells off the board
+        assert_eq!(Map::pixel_to_cell((-1, -1)), (-1, -4));
+        assert_eq!(Map::pixel_to_cell((0, 0)), (0, -3));
+        assert_eq!(
+            Map::pixel_to_cell(((BOARD_WIDTH * CELL_SIZE) as i32, pixel.1)),
+            (BOARD_WIDTH as i32, 7)
+        );
+    }
+
+    #[test]
+    fn ghost_house_is_separate_from_playfield() {
+        let map = Map::new(RAW_BOARD);
+        let components = map.connected_components();
+        let component_of = |cell| {
+            components
+                .iter()
+                .position(|component| component.contains(&cell))
+        };
+
+        // Every pellet is reachable from Pac-Man's starting position
+        let playfield = component_of((13, 23)).unwrap();
+        for x in 0..BOARD_WIDTH {
+            for y in 0..BOARD_HEIGHT {
+                if matches!(
+                    map.get_tile((x as i32, y as i32)),
+                    Some(MapTile::Pellet | MapTile::PowerPellet)
+                ) {
+                    assert_eq!(component_of((x, y)), Some(playfield), "{:?}", (x, y));
+                }
+            }
+        }
+
+        // The door closes off the ghost house, and isn't part of either region
+        assert_eq!(map.get_tile((13, 12)), Some(MapTile::Door));
+        assert_eq!(component_of((13, 12)), None);
+        let house = component_of((11, 14)).unwrap();
+        assert_ne!(house, playfield);
+        assert_eq!(component_of((15, 14)), Some(house));
     }
 }
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 
 use sdl2::{
@@ -9,27 +10,36 @@ use tracing::event;
 use crate::{
     animation::AnimatedTexture,
     constants::MapTile,
-    constants::{BOARD_OFFSET, CELL_SIZE},
+    constants::{BOARD_WIDTH, CELL_SIZE},
     direction::Direction,
     entity::Entity,
     map::Map,
     modulation::{SimpleTickModulator, TickModulator},
 };
 
+// How far the sprite extends past its cell on each side
+const SPRITE_MARGIN: i32 = 4;
+
 pub struct Pacman<'a> {
     // Absolute position on the board (precise)
     pub position: (i32, i32),
     pub direction: Direction,
     pub next_direction: Option<Direction>,
     pub stopped: bool,
-    map: Rc<Map>,
+    // Inverts requested directions (e.g. Up becomes Down) before they are buffered
+    pub controls_reversed: bool,
+    map: Rc<RefCell<Map>>,
     speed: u32,
     modulation: SimpleTickModulator,
     sprite: AnimatedTexture<'a>,
 }
 
 impl Pacman<'_> {
-    pub fn new<'a>(starting_position: (u32, u32), atlas: Texture<'a>, map: Rc<Map>) -> Pacman<'a> {
+    pub fn new<'a>(
+        starting_position: (u32, u32),
+        atlas: Texture<'a>,
+        map: Rc<RefCell<Map>>,
+    ) -> Pacman<'a> {
         Pacman {
             position: Map::cell_to_pixel(starting_position),
             direction: Direction::Right,
@@ -37,12 +47,22 @@ impl Pacman<'_> {
             speed: 3,
             map,
             stopped: false,
+            controls_reversed: false,
             modulation: SimpleTickModulator::new(1.0),
-            sprite: AnimatedTexture::new(atlas, 2, 3, 32, 32, Some((-4, -4))),
+            sprite: AnimatedTexture::new(
+                atlas,
+                2,
+                3,
+                32,
+                32,
+                Some((-SPRITE_MARGIN, -SPRITE_MARGIN)),
+            ),
         }
     }
 
     pub fn render(&mut self, canvas: &mut Canvas<Window>) {
+        self.render_wrapped(canvas);
+
         // When stopped, render the last frame of the animation
         if self.stopped {
             self.sprite
@@ -52,6 +72,21 @@ impl Pacman<'_> {
         }
     }
 
+    // Renders the current frame without advancing the animation (e.g. while paused)
+    pub fn render_frozen(&mut self, canvas: &mut Canvas<Window>) {
+        self.render_wrapped(canvas);
+        self.sprite
+            .render_static(canvas, self.position, self.direction, None);
+    }
+
+    // While passing through the tunnel, also draw the part of the sprite that has wrapped onto the other side
+    fn render_wrapped(&mut self, canvas: &mut Canvas<Window>) {
+        if let Some(wrapped) = wrapped_position(self.position) {
+            self.sprite
+                .render_static(canvas, wrapped, self.direction, None);
+        }
+    }
+
     pub fn next_cell(&self, direction: Option<Direction>) -> (i32, i32) {
         let (x, y) = direction.unwrap_or(self.direction).offset();
         let cell = self.cell_position();
@@ -66,13 +101,22 @@ impl Pacman<'_> {
             self.next_direction = None;
             return;
         }
+        // Reversals are handled as soon as they're requested, so only turns are left to take at a cell
+        if !self
+            .direction
+            .perpendicular()
+            .contains(&self.next_direction.unwrap())
+        {
+            return;
+        }
 
         let proposed_next_cell = self.next_cell(self.next_direction);
         let proposed_next_tile = self
             .map
+            .borrow()
             .get_tile(proposed_next_cell)
             .unwrap_or(MapTile::Empty);
-        if proposed_next_tile != MapTile::Wall {
+        if proposed_next_tile.is_passable() {
             self.direction = self.next_direction.unwrap();
             self.next_direction = None;
         }
@@ -84,6 +128,46 @@ impl Pacman<'_> {
     }
 }
 
+// The position of the sprite's copy on the opposite side of the board, if the sprite overlaps either edge
+fn wrapped_position((x, y): (i32, i32)) -> Option<(i32, i32)> {
+    let board_width = (BOARD_WIDTH * CELL_SIZE) as i32;
+
+    if x < SPRITE_MARGIN {
+        Some((x + board_width, y))
+    } else if x + CELL_SIZE as i32 + SPRITE_MARGIN > board_width {
+        Some((x - board_width, y))
+    } else {
+        None
+    }
+}
+
+// Moves a position in a direction, wrapping around through the tunnel. The board width is a multiple of both the cell
+// size and speed, so the position stays aligned with the grid.
+pub fn advance(position: (i32, i32), direction: Direction, speed: u32) -> (i32, i32) {
+    let (dx, dy) = direction.offset();
+    let speed = speed as i32;
+    let board_width = (BOARD_WIDTH * CELL_SIZE) as i32;
+
+    (
+        (position.0 + dx * speed).rem_euclid(board_width),
+        position.1 + dy * speed,
+    )
+}
+
+// Turns around immediately if the opposite direction was requested and it is possible to, returning the new direction
+// and buffered request. Reversing is allowed at any time, not just once aligned with a cell (as in the arcade).
+fn reverse_if_requested(
+    direction: Direction,
+    next_direction: Option<Direction>,
+    can_reverse: bool,
+) -> (Direction, Option<Direction>) {
+    if can_reverse && next_direction == Some(direction.opposite()) {
+        (direction.opposite(), None)
+    } else {
+        (direction, next_direction)
+    }
+}
+
 impl Entity for Pacman<'_> {
     fn is_colliding(&self, other: &dyn Entity) -> bool {
         let (x, y) = self.position();
@@ -96,11 +180,8 @@ impl Entity for Pacman<'_> {
     }
 
     fn cell_position(&self) -> (u32, u32) {
-        let (x, y) = self.position;
-        (
-            (x as u32 / CELL_SIZE) - BOARD_OFFSET.0,
-            (y as u32 / CELL_SIZE) - BOARD_OFFSET.1,
-        )
+        let (x, y) = Map::pixel_to_cell(self.position);
+        (x as u32, y as u32)
     }
 
     fn internal_position(&self) -> (u32, u32) {
@@ -109,39 +190,108 @@ impl Entity for Pacman<'_> {
     }
 
     fn tick(&mut self) {
+        // Between cells Pac-Man can always head back the way he came, but from a cell it must be open behind him
+        let behind = self.next_cell(Some(self.direction.opposite()));
+        let can_reverse = self.internal_position() != (0, 0)
+            || self
+                .map
+                .borrow()
+                .get_tile(behind)
+                .unwrap_or(MapTile::Empty)
+                .is_passable();
+        (self.direction, self.next_direction) =
+            reverse_if_requested(self.direction, self.next_direction, can_reverse);
+
         let can_change = self.internal_position_even() == (0, 0);
 
         if can_change {
             self.handle_requested_direction();
 
             let next = self.next_cell(None);
-            let next_tile = self.map.get_tile(next).unwrap_or(MapTile::Empty);
+            let next_tile = self.map.borrow().get_tile(next).unwrap_or(MapTile::Empty);
 
-            if !self.stopped && next_tile == MapTile::Wall {
+            if !self.stopped && !next_tile.is_passable() {
                 event!(tracing::Level::DEBUG, "Wall collision. Stopping.");
                 self.stopped = true;
-            } else if self.stopped && next_tile != MapTile::Wall {
+            } else if self.stopped && next_tile.is_passable() {
                 event!(tracing::Level::DEBUG, "Wall collision resolved. Moving.");
                 self.stopped = false;
             }
         }
         
         if !self.stopped && self.modulation.next() {
-            let speed = self.speed as i32;
-            match self.direction {
-                Direction::Right => {
-                    self.position.0 += speed;
-                }
-                Direction::Left => {
-                    self.position.0 -= speed;
-                }
-                Direction::Up => {
-                    self.position.1 -= speed;
-                }
-                Direction::Down => {
-                    self.position.1 += speed;
-                }
-            }
+            self.position = advance(self.position, self.direction, self.speed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverses_immediately() {
+        assert_eq!(
+            reverse_if_requested(Direction::Right, Some(Direction::Left), true),
+            (Direction::Left, None)
+        );
+        assert_eq!(
+            reverse_if_requested(Direction::Up, Some(Direction::Down), true),
+            (Direction::Down, None)
+        );
+
+        // Stopped at a dead end, there's nowhere to turn around to, so the request stays buffered
+        assert_eq!(
+            reverse_if_requested(Direction::Right, Some(Direction::Left), false),
+            (Direction::Right, Some(Direction::Left))
+        );
+    }
+
+    #[test]
+    fn turns_wait_for_alignment() {
+        assert_eq!(
+            reverse_if_requested(Direction::Right, Some(Direction::Up), true),
+            (Direction::Right, Some(Direction::Up))
+        );
+        assert_eq!(
+            reverse_if_requested(Direction::Right, None, true),
+            (Direction::Right, None)
+        );
+    }
+
+    #[test]
+    fn wraps_through_tunnel() {
+        assert_eq!(advance((0, 336), Direction::Left, 3), (669, 336));
+        assert_eq!(advance((669, 336), Direction::Right, 3), (0, 336));
+        assert_eq!(advance((300, 336), Direction::Up, 3), (300, 333));
+    }
+
+    #[test]
+    fn wrapped_copy_is_drawn_at_both_edges() {
+        let board_width = (BOARD_WIDTH * CELL_SIZE) as i32;
+
+        assert_eq!(wrapped_position((0, 336)), Some((672, 336)));
+        assert_eq!(wrapped_position((669, 336)), Some((-3, 336)));
+        // The sprite overlaps each edge by the same amount when the copy starts being drawn
+        assert_eq!(wrapped_position((3, 336)), Some((675, 336)));
+        assert_eq!(wrapped_position((4, 336)), None);
+        assert_eq!(wrapped_position((board_width - 28, 336)), None);
+        assert_eq!(wrapped_position((board_width - 27, 336)), Some((-27, 336)));
+        assert_eq!(wrapped_position((324, 336)), None);
+    }
+
+    #[test]
+    fn tunnel_crossing_is_continuous() {
+        let board_width = (BOARD_WIDTH * CELL_SIZE) as i32;
+        let mut position = (24, 336);
+
+        for _ in 0..20 {
+            let next = advance(position, Direction::Left, 3);
+            // Either the sprite itself or its copy on the other side moved by exactly one step
+            let copies = [Some(next), wrapped_position(next)];
+            assert!(copies.contains(&Some((position.0 - 3, position.1))));
+            assert_eq!((position.0 - next.0).rem_euclid(board_width), 3);
+            position = next;
         }
     }
 }
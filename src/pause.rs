@@ -0,0 +1,197 @@
+use std::time::{Duration, Instant};
+
+/// Whether the game is paused, and whether that pause was engaged automatically by losing window focus.
+///
+/// An automatic pause is lifted when focus returns, but a manual pause is never overridden by focus changes.
+#[derive(Debug, Default)]
+pub struct PauseState {
+    paused: bool,
+    // Whether the current pause was engaged by losing focus (rather than manually)
+    auto_paused: bool,
+}
+
+impl PauseState {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Manually pauses or unpauses. Returns whether the game is now paused.
+    pub fn toggle(&mut self) -> bool {
+        self.paused = !self.paused;
+        self.auto_paused = false;
+        self.paused
+    }
+
+    // Pauses automatically, unless already paused. Returns whether the pause was engaged.
+    pub fn focus_lost(&mut self) -> bool {
+        if self.paused {
+            return false;
+        }
+
+        self.paused = true;
+        self.auto_paused = true;
+        true
+    }
+
+    // Lifts an automatic pause. Returns whether the game was unpaused.
+    pub fn focus_gained(&mut self) -> bool {
+        if !self.auto_paused {
+            return false;
+        }
+
+        self.paused = false;
+        self.auto_paused = false;
+        true
+    }
+
+    // Whether frames should be drawn dimmed. Every pause lasts until it is lifted (there is no single-tick stepping),
+    // so this is any pause.
+    pub fn dims(&self) -> bool {
+        self.paused
+    }
+}
+
+/// A quit request awaiting confirmation, when quitting has to be confirmed.
+///
+/// The first request only asks for confirmation. Requesting again (or confirming) within the timeout quits, and anything
+/// else cancels.
+pub struct QuitConfirm {
+    timeout: Duration,
+    // When quitting was last requested, if it is awaiting confirmation
+    requested: Option<Instant>,
+}
+
+impl QuitConfirm {
+    pub fn new(timeout: Duration) -> Self {
+        QuitConfirm {
+            timeout,
+            requested: None,
+        }
+    }
+
+    // Whether a quit request is awaiting confirmation
+    pub fn pending(&self, now: Instant) -> bool {
+        self.requested
+            .is_some_and(|requested| now.duration_since(requested) < self.timeout)
+    }
+
+    // Requests to quit, returning whether to quit now. Holding the key down (a repeat) doesn't count as confirming.
+    pub fn request(&mut self, now: Instant, repeat: bool) -> bool {
+        if self.pending(now) {
+            return !repeat;
+        }
+
+        self.requested = Some(now);
+        false
+    }
+
+    // Confirms a pending quit request, returning whether to quit now
+    pub fn confirm(&self, now: Instant) -> bool {
+        self.pending(now)
+    }
+
+    // Cancels a pending quit request
+    pub fn cancel(&mut self) {
+        self.requested = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_loss_pauses_until_regained() {
+        let mut pause = PauseState::default();
+
+        assert!(pause.focus_lost());
+        assert!(pause.is_paused());
+
+        assert!(pause.focus_gained());
+        assert!(!pause.is_paused());
+    }
+
+    #[test]
+    fn focus_changes_keep_manual_pause() {
+        let mut pause = PauseState::default();
+        pause.toggle();
+
+        assert!(!pause.focus_lost());
+        assert!(!pause.focus_gained());
+        assert!(pause.is_paused());
+    }
+
+    #[test]
+    fn manual_unpause_clears_auto_pause() {
+        let mut pause = PauseState::default();
+        pause.focus_lost();
+        pause.toggle();
+        pause.toggle();
+
+        // The game was paused manually the second time, so regaining focus leaves it paused
+        assert!(!pause.focus_gained());
+        assert!(pause.is_paused());
+    }
+
+    #[test]
+    fn dims_while_paused() {
+        let mut pause = PauseState::default();
+        assert!(!pause.dims());
+
+        pause.toggle();
+        assert!(pause.dims());
+        pause.toggle();
+        assert!(!pause.dims());
+
+        pause.focus_lost();
+        assert!(pause.dims());
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(3);
+
+    #[test]
+    fn single_quit_request_asks_for_confirmation() {
+        let start = Instant::now();
+        let mut quit = QuitConfirm::new(TIMEOUT);
+
+        assert!(!quit.request(start, false));
+        assert!(quit.pending(start));
+        // Holding the key down doesn't confirm
+        assert!(!quit.request(start + Duration::from_millis(500), true));
+        assert!(quit.request(start + Duration::from_secs(1), false));
+    }
+
+    #[test]
+    fn quit_request_can_be_confirmed() {
+        let start = Instant::now();
+        let mut quit = QuitConfirm::new(TIMEOUT);
+
+        assert!(!quit.confirm(start));
+        quit.request(start, false);
+        assert!(quit.confirm(start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn quit_request_expires() {
+        let start = Instant::now();
+        let mut quit = QuitConfirm::new(TIMEOUT);
+        quit.request(start, false);
+
+        let later = start + TIMEOUT;
+        assert!(!quit.confirm(later));
+        // Requesting again after the timeout asks for confirmation again
+        assert!(!quit.request(later, false));
+        assert!(quit.pending(later));
+    }
+
+    #[test]
+    fn quit_request_can_be_cancelled() {
+        let start = Instant::now();
+        let mut quit = QuitConfirm::new(TIMEOUT);
+        quit.request(start, false);
+        quit.cancel();
+
+        assert!(!quit.pending(start));
+        assert!(!quit.request(start, false));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use tracing::event;
+
+/// Skips rendering every other frame while the game loop is running behind.
+///
+/// Gameplay still ticks every frame so input stays responsive; only drawing is dropped. The decision is based on a
+/// rolling average of full (rendered) frame times, so a single slow frame won't trigger skipping, and skipping is
+/// disabled again once rendered frames fit within the budget.
+pub struct RenderSkip {
+    budget: Duration,
+    samples: VecDeque<Duration>,
+    skipping: bool,
+    skipped_last: bool,
+}
+
+impl RenderSkip {
+    // Number of rendered frames the rolling average is computed over
+    const SAMPLES: usize = 30;
+
+    pub fn new(budget: Duration) -> Self {
+        RenderSkip {
+            budget,
+            samples: VecDeque::with_capacity(Self::SAMPLES),
+            skipping: false,
+            skipped_last: false,
+        }
+    }
+
+    // Record how long a rendered frame took to process (excluding sleep)
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.samples.len() == Self::SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+
+        // Only decide once the window is full, otherwise startup hitches dominate the average
+        if self.samples.len() < Self::SAMPLES {
+            return;
+        }
+
+        let average = self.samples.iter().sum::<Duration>() / self.samples.len() as u32;
+        let over_budget = average > self.budget;
+        if over_budget != self.skipping {
+            self.skipping = over_budget;
+            event!(
+                tracing::Level::INFO,
+                "Render skipping {} (average frame time {:?})",
+                if over_budget { "enabled" } else { "disabled" },
+                average
+            );
+        }
+    }
+
+    // Returns whether the current frame should be rendered
+    pub fn should_render(&mut self) -> bool {
+        if !self.skipping {
+            self.skipped_last = false;
+            return true;
+        }
+
+        // Alternate between rendering and skipping
+        self.skipped_last = !self.skipped_last;
+        !self.skipped_last
+    }
+}
+
+/// Tracks rolling frames-per-second averages over the last 1 and 10 seconds.
+///
+/// Frame timestamps are kept in a ring buffer, and anything older than the longest window is dropped as new frames
+/// are recorded.
+#[derive(Default)]
+pub struct FpsCounter {
+    frames: VecDeque<Instant>,
+}
+
+impl FpsCounter {
+    const SHORT_WINDOW: Duration = Duration::from_secs(1);
+    const LONG_WINDOW: Duration = Duration::from_secs(10);
+
+    // Record a frame completing at the given time
+    pub fn record(&mut self, now: Instant) {
+        while let Some(&oldest) = self.frames.front() {
+            if now.duration_since(oldest) <= Self::LONG_WINDOW {
+                break;
+            }
+            self.frames.pop_front();
+        }
+        self.frames.push_back(now);
+    }
+
+    // Returns the (1 second, 10 second) average FPS
+    pub fn fps(&self) -> (f32, f32) {
+        (
+            self.average(Self::SHORT_WINDOW),
+            self.average(Self::LONG_WINDOW),
+        )
+    }
+
+    // Average FPS over the frames recorded within the given window of the latest frame
+    fn average(&self, window: Duration) -> f32 {
+        let Some(&latest) = self.frames.back() else {
+            return 0.0;
+        };
+
+        let count = self
+            .frames
+            .iter()
+            .rev()
+            .take_while(|&&frame| latest.duration_since(frame) <= window)
+            .count();
+
+        // Measure from the oldest frame in the window, so the average is sensible before the window has filled
+        let oldest = self.frames[self.frames.len() - count];
+        let span = latest.duration_since(oldest).as_secs_f32();
+        if span == 0.0 {
+            return 0.0;
+        }
+
+        (count - 1) as f32 / span
+    }
+}
+
+/// Limits how often a repeated warning is logged, counting the occurrences suppressed in between.
+pub struct LogLimiter {
+    interval: Duration,
+    last: Option<Instant>,
+    suppressed: u32,
+}
+
+impl LogLimiter {
+    pub fn new(interval: Duration) -> Self {
+        LogLimiter {
+            interval,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    // Returns the number of occurrences suppressed since the last one if this occurrence should be logged,
+    // or None if it should be suppressed.
+    pub fn allow(&mut self, now: Instant) -> Option<u32> {
+        if let Some(last) = self.last {
+            if now.duration_since(last) < self.interval {
+                self.suppressed += 1;
+                return None;
+            }
+        }
+
+        self.last = Some(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fps_averages() {
+        let start = Instant::now();
+        let mut counter = FpsCounter::default();
+        assert_eq!(counter.fps(), (0.0, 0.0));
+
+        // 10 seconds at 50 FPS, then a second at 25 FPS
+        let mut now = start;
+        for _ in 0..500 {
+            counter.record(now);
+            now += Duration::from_millis(20);
+        }
+        for _ in 0..25 {
+            counter.record(now);
+            now += Duration::from_millis(40);
+        }
+        counter.record(now);
+
+        let (short, long) = counter.fps();
+        assert!((short - 25.0).abs() < 0.01, "1s average was {}", short);
+        assert!((long - 47.5).abs() < 0.01, "10s average was {}", long);
+    }
+
+    #[test]
+    fn render_skip_follows_average_frame_time() {
+        let budget = Duration::from_millis(10);
+        let mut skip = RenderSkip::new(budget);
+
+        // Skipping waits for a full window of slow frames
+        for _ in 0..RenderSkip::SAMPLES - 1 {
+            skip.record(budget * 2);
+            assert!(skip.should_render());
+        }
+        skip.record(budget * 2);
+        assert!(!skip.should_render());
+        assert!(skip.should_render());
+        assert!(!skip.should_render());
+        assert!(skip.should_render());
+
+        // Fast frames bring the average back within budget
+        for _ in 0..RenderSkip::SAMPLES {
+            skip.record(budget / 2);
+        }
+        assert!(skip.should_render());
+        assert!(skip.should_render());
+    }
+
+    #[test]
+    fn log_limiter_counts_suppressed() {
+        let start = Instant::now();
+        let mut limiter = LogLimiter::new(Duration::from_secs(1));
+
+        assert_eq!(limiter.allow(start), Some(0));
+        assert_eq!(limiter.allow(start + Duration::from_millis(100)), None);
+        assert_eq!(limiter.allow(start + Duration::from_millis(500)), None);
+        assert_eq!(limiter.allow(start + Duration::from_secs(1)), Some(2));
+        assert_eq!(limiter.allow(start + Duration::from_millis(1100)), None);
+    }
+}